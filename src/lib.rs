@@ -1,47 +1,131 @@
 //! Display the input and output structure of an ONNX model.
-use ndarray::Axis;
+use hf_hub::api::sync::Api;
+use hf_hub::{Cache, Repo, RepoType};
+use ndarray::{Array2, Array3, ArrayD, Axis};
+use onnxruntime::session::SessionBuilder;
 use onnxruntime::{environment::Environment, session::Session, tensor::OrtOwnedTensor};
 use tokenizers::tokenizer::{Result, Tokenizer};
+use tokenizers::{PaddingParams, PaddingStrategy};
+
+// NOTE: the `onnxruntime` crate this file is built on (nbigaouette/onnxruntime-rs)
+// has no execution-provider selection and no global thread-pool configuration
+// API — both live only in the unrelated `ort` crate. GPU execution providers
+// and a shared thread pool are therefore not offered here; `SessionConfig`
+// only covers what `onnxruntime` actually exposes (graph optimization level).
+
+/// Per-session settings applied every time a `Client` builds a `ClientSession`.
+#[derive(Clone, Debug)]
+pub struct SessionConfig {
+    pub optimization_level: onnxruntime::GraphOptimizationLevel,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            optimization_level: onnxruntime::GraphOptimizationLevel::Basic,
+        }
+    }
+}
 
 pub struct Client {
     environment: Environment,
+    session_config: SessionConfig,
 }
 
 pub struct ClientSession<'a> {
     session: Session<'a>,
     tokenizer: Tokenizer,
+    pooling: PoolingConfig,
+    normalize: bool,
+    is_masked_lm: bool,
+    query_prefix: String,
+    document_prefix: String,
+    distribution_shift: Option<DistributionShift>,
 }
 
-impl Client {
+/// Parameters of the raw similarity-score distribution a model produces,
+/// used to remap scores onto a roughly uniform [0,1] scale so a single
+/// relevance threshold works across models with different score ranges.
+#[derive(Clone, Copy, Debug)]
+pub struct DistributionShift {
+    pub mean: f32,
+    pub std: f32,
+}
+
+impl DistributionShift {
+    fn shift(&self, score: f32) -> f32 {
+        1.0 / (1.0 + (-(score - self.mean) / self.std).exp())
+    }
+}
+
+/// How token-level hidden states are reduced to a single sentence vector.
+/// Must match the pooling the loaded model was trained with: BERT-style
+/// sentence-transformers models typically use `Mean`, while many BGE/E5
+/// models expect `Cls`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PoolingConfig {
+    #[default]
+    Mean,
+    Cls,
+    MaxTokens,
+}
+
+/// Builds a `Client`, letting callers configure the graph optimization
+/// level used by every session it builds.
+pub struct ClientBuilder {
+    name: String,
+    log_level: onnxruntime::LoggingLevel,
+    session_config: SessionConfig,
+}
+
+impl ClientBuilder {
     pub fn new() -> Self {
-        // Initialize the ONNX runtime environment and load the model
+        Self {
+            name: "onnx metadata".to_string(),
+            log_level: onnxruntime::LoggingLevel::Verbose,
+            session_config: SessionConfig::default(),
+        }
+    }
+
+    pub fn with_optimization_level(mut self, level: onnxruntime::GraphOptimizationLevel) -> Self {
+        self.session_config.optimization_level = level;
+        self
+    }
+
+    pub fn build(self) -> Client {
         let environment = Environment::builder()
-            .with_name("onnx metadata")
-            .with_log_level(onnxruntime::LoggingLevel::Verbose)
+            .with_name(&self.name)
+            .with_log_level(self.log_level)
             .build()
             .unwrap();
 
-        Self { environment }
+        Client {
+            environment,
+            session_config: self.session_config,
+        }
+    }
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    pub fn new() -> Self {
+        ClientBuilder::new().build()
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
     }
 
     pub fn init_with_path(&self, model_path: String) -> ClientSession {
         let tokenizer_path = format!("{}/tokenizer.json", model_path);
         let model_path = format!("{}/model.onnx", model_path);
 
-        // Create a new session with optimizations
-        let session = self
-            .environment
-            .new_session_builder()
-            .unwrap()
-            .with_optimization_level(onnxruntime::GraphOptimizationLevel::Basic)
-            .unwrap()
-            .with_model_from_file(model_path)
-            .unwrap();
-
-        // Load the tokenizer and encode the input
-        let tokenizer = Tokenizer::from_file(tokenizer_path).unwrap();
-
-        ClientSession { session, tokenizer }
+        self.init_with_path_parts(model_path.into(), tokenizer_path.into())
     }
 
     // We need B1 and B2 as both arrays may have different sizes. We cannot
@@ -54,18 +138,23 @@ impl Client {
     ) -> ClientSession {
         // Create a new session with optimizations
         let session = self
-            .environment
-            .new_session_builder()
-            .unwrap()
-            .with_optimization_level(onnxruntime::GraphOptimizationLevel::Basic)
-            .unwrap()
+            .configure_session_builder(self.environment.new_session_builder().unwrap())
             .with_model_from_memory(model_bytes)
             .unwrap();
 
         // Load the tokenizer and encode the input
         let tokenizer = Tokenizer::from_bytes(tokenizer_bytes).unwrap();
 
-        ClientSession { session, tokenizer }
+        ClientSession {
+            session,
+            tokenizer,
+            pooling: PoolingConfig::default(),
+            normalize: true,
+            is_masked_lm: false,
+            query_prefix: String::new(),
+            document_prefix: String::new(),
+            distribution_shift: None,
+        }
     }
 
     pub fn init_defaults(&self) -> ClientSession {
@@ -74,64 +163,413 @@ impl Client {
             std::include_bytes!("../onnx/tokenizer.json"),
         )
     }
+
+    /// Resolves `model.onnx` and `tokenizer.json` for `model_id` from the
+    /// Hugging Face Hub at `revision` (defaulting to the `"main"` branch).
+    /// Passing a commit hash pins the files loaded so they are reproducible
+    /// across runs; a branch name follows the branch. Both files are cached
+    /// locally by `hf-hub` after the first fetch. Set `HF_HUB_OFFLINE=1` to
+    /// resolve only from that local cache: no network request is made, and
+    /// this panics if either file isn't already cached.
+    pub fn init_from_hub(&self, model_id: &str, revision: Option<&str>) -> ClientSession {
+        let repo = Repo::with_revision(
+            model_id.to_string(),
+            RepoType::Model,
+            revision.unwrap_or("main").to_string(),
+        );
+
+        let (model_path, tokenizer_path) = if std::env::var("HF_HUB_OFFLINE").is_ok() {
+            let cached_repo = Cache::from_env().repo(repo);
+            let model_path = cached_repo
+                .get("model.onnx")
+                .expect("model.onnx not found in local Hugging Face cache (HF_HUB_OFFLINE is set)");
+            let tokenizer_path = cached_repo.get("tokenizer.json").expect(
+                "tokenizer.json not found in local Hugging Face cache (HF_HUB_OFFLINE is set)",
+            );
+            (model_path, tokenizer_path)
+        } else {
+            let api_repo = Api::new().unwrap().repo(repo);
+            let model_path = api_repo.get("model.onnx").unwrap();
+            let tokenizer_path = api_repo.get("tokenizer.json").unwrap();
+            (model_path, tokenizer_path)
+        };
+
+        self.init_with_path_parts(model_path, tokenizer_path)
+    }
+
+    fn init_with_path_parts(
+        &self,
+        model_path: std::path::PathBuf,
+        tokenizer_path: std::path::PathBuf,
+    ) -> ClientSession {
+        let session = self
+            .configure_session_builder(self.environment.new_session_builder().unwrap())
+            .with_model_from_file(model_path)
+            .unwrap();
+
+        let tokenizer = Tokenizer::from_file(tokenizer_path).unwrap();
+
+        ClientSession {
+            session,
+            tokenizer,
+            pooling: PoolingConfig::default(),
+            normalize: true,
+            is_masked_lm: false,
+            query_prefix: String::new(),
+            document_prefix: String::new(),
+            distribution_shift: None,
+        }
+    }
+
+    /// Applies this client's optimization level to a freshly created
+    /// session builder.
+    fn configure_session_builder<'a>(&'a self, builder: SessionBuilder<'a>) -> SessionBuilder<'a> {
+        builder
+            .with_optimization_level(self.session_config.optimization_level)
+            .unwrap()
+    }
 }
 
-// TODO: Create a client so we only initialize the environment once
-// then we can call the client with the input and get the output
+// Stacks a batch of tokenizer encodings into the `(N, max_len)`
+// `input_ids`/`attention_mask`/`token_type_ids` arrays the ONNX session
+// expects. `encodings` must already be padded to a common length (e.g. via
+// `Tokenizer::with_padding`), as is the case for both a `Tokenizer::encode`
+// result wrapped in a single-element slice and an `encode_batch` result.
+fn encodings_to_arrays(
+    encodings: &[tokenizers::Encoding],
+) -> (Array2<i64>, Array2<i64>, Array2<i64>) {
+    let batch_size = encodings.len();
+    let max_len = encodings[0].get_ids().len();
+
+    let mut input_ids = Vec::with_capacity(batch_size * max_len);
+    let mut attention_mask = Vec::with_capacity(batch_size * max_len);
+    let mut token_type_ids = Vec::with_capacity(batch_size * max_len);
+
+    for encoding in encodings {
+        input_ids.extend(encoding.get_ids().iter().map(|&x| x as i64));
+        attention_mask.extend(encoding.get_attention_mask().iter().map(|&x| x as i64));
+        token_type_ids.extend(encoding.get_type_ids().iter().map(|&x| x as i64));
+    }
+
+    (
+        Array2::from_shape_vec((batch_size, max_len), input_ids).unwrap(),
+        Array2::from_shape_vec((batch_size, max_len), attention_mask).unwrap(),
+        Array2::from_shape_vec((batch_size, max_len), token_type_ids).unwrap(),
+    )
+}
+
+// Broadcasts the `(N, seq_len)` attention mask out to `(N, seq_len, hidden)`
+// as f32 so it lines up with the token embeddings for masked reductions.
+fn expand_mask(attention_mask: &Array2<i64>, hidden_size: usize) -> Array3<f32> {
+    attention_mask
+        .clone()
+        .insert_axis(Axis(2))
+        .broadcast((attention_mask.nrows(), attention_mask.ncols(), hidden_size))
+        .unwrap()
+        .mapv(|x| x as f32)
+}
+
+fn mean_pool(token_embeddings: &ArrayD<f32>, attention_mask: &Array2<i64>) -> ArrayD<f32> {
+    let mask_expanded = expand_mask(attention_mask, token_embeddings.shape()[2]);
+    let token_masked_sum = (token_embeddings * &mask_expanded).sum_axis(Axis(1));
+    let mask_sum = mask_expanded.sum_axis(Axis(1)).mapv(|x| x.max(1e-9));
+    token_masked_sum / mask_sum
+}
+
+// Takes the first token's hidden state, which is always the `[CLS]` token
+// and is never padded away under `PaddingStrategy::BatchLongest`.
+fn cls_pool(token_embeddings: &ArrayD<f32>) -> ArrayD<f32> {
+    token_embeddings.index_axis(Axis(1), 0).to_owned()
+}
+
+// Element-wise max over unmasked tokens: padded positions are pushed to
+// -inf first so they can never win the max.
+fn max_pool(token_embeddings: &ArrayD<f32>, attention_mask: &Array2<i64>) -> ArrayD<f32> {
+    let mask_expanded = expand_mask(attention_mask, token_embeddings.shape()[2]);
+    let masked = token_embeddings - (1.0 - &mask_expanded) * 1e9;
+    masked.fold_axis(Axis(1), f32::NEG_INFINITY, |&acc, &x| acc.max(x))
+}
+
+fn pool(
+    token_embeddings: &ArrayD<f32>,
+    attention_mask: &Array2<i64>,
+    pooling: PoolingConfig,
+) -> ArrayD<f32> {
+    match pooling {
+        PoolingConfig::Mean => mean_pool(token_embeddings, attention_mask),
+        PoolingConfig::Cls => cls_pool(token_embeddings),
+        PoolingConfig::MaxTokens => max_pool(token_embeddings, attention_mask),
+    }
+}
+
+fn normalize_rows(embeddings: ArrayD<f32>) -> ArrayD<f32> {
+    let l2_norm = embeddings
+        .mapv(|x| x.powi(2))
+        .sum_axis(Axis(1))
+        .mapv(f32::sqrt)
+        .insert_axis(Axis(1));
+
+    embeddings / l2_norm
+}
+
+// Returns `None` instead of a nonsensical score when `a` and `b` have
+// different lengths (e.g. embeddings from two different models).
+fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() {
+        return None;
+    }
+
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x.powi(2)).sum::<f32>().sqrt();
+
+    Some(dot / (norm_a * norm_b).max(1e-9))
+}
 
 impl ClientSession<'_> {
     pub fn embedding<'a>(&mut self, input: &'a str) -> Result<Vec<f32>> {
-        let encoding = self.tokenizer.encode(input, true)?;
+        Ok(self
+            .embed_batch(&[input])?
+            .into_iter()
+            .next()
+            .expect("embed_batch returns one row per input"))
+    }
 
-        // Convert the encoded input to the format expected by the ONNX model
-        let input_ids: Vec<i64> = encoding.get_ids().iter().map(|&x| x as i64).collect();
-        let attention_mask: Vec<i64> = encoding
-            .get_attention_mask()
-            .iter()
-            .map(|&x| x as i64)
-            .collect();
+    /// Embeds a batch of inputs in a single ONNX call. Inputs are padded to
+    /// the length of the longest sequence in the batch before the
+    /// `input_ids`/`attention_mask`/`token_type_ids` tensors are stacked
+    /// into `(N, max_len)` arrays, so this amortizes both the tokenizer and
+    /// the model call across the whole batch instead of paying their cost
+    /// once per input.
+    pub fn embed_batch(&mut self, inputs: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            ..Default::default()
+        }));
 
-        // Prepare the input tensors
-        let token_type_ids: Vec<i64> = encoding.get_type_ids().iter().map(|&x| x as i64).collect();
-        let input_ids_array =
-            ndarray::Array::from_shape_vec((1, input_ids.len()), input_ids).unwrap();
-        let attention_mask_array =
-            ndarray::Array::from_shape_vec((1, attention_mask.len()), attention_mask).unwrap();
-        let token_type_ids_array =
-            ndarray::Array::from_shape_vec((1, token_type_ids.len()), token_type_ids).unwrap();
+        let encodings = self.tokenizer.encode_batch(inputs.to_vec(), true)?;
+        let (input_ids_array, attention_mask_array, token_type_ids_array) =
+            encodings_to_arrays(&encodings);
 
-        // Run the model on the input tensors and retrieve the output
+        // Run the model once on the whole batch and retrieve the output
         let outputs: Vec<OrtOwnedTensor<f32, _>> = self.session.run(vec![
-            input_ids_array.clone(),
+            input_ids_array,
             attention_mask_array.clone(),
             token_type_ids_array,
         ])?;
 
-        // Extract and expand the token embeddings
+        // Extract the token embeddings and pool each row independently
         let token_embeddings = outputs[0].to_owned();
-        let input_mask_expanded = attention_mask_array
-            .clone()
-            .insert_axis(Axis(2))
-            .broadcast((
-                attention_mask_array.nrows(),
-                attention_mask_array.ncols(),
-                token_embeddings.shape()[2],
-            ))
-            .unwrap()
-            .mapv(|x| x as f32);
-
-        // Calculate the sentence embeddings from the output
-        let token_masked_sum = (&token_embeddings * &input_mask_expanded).sum_axis(Axis(1));
-        let mask_sum = input_mask_expanded.sum_axis(Axis(1)).mapv(|x| x.max(1e-9));
-        let mean_pooling = token_masked_sum / mask_sum;
-        let l2_norm = mean_pooling.mapv(|x| x.powi(2)).sum().sqrt();
-        let sentence_embeddings = mean_pooling / l2_norm;
-
-        // Convert to Vec<f32>
-        let mut vec = Vec::new();
-        for i in sentence_embeddings.iter() {
-            vec.push(*i);
+        let pooled = pool(&token_embeddings, &attention_mask_array, self.pooling);
+        let sentence_embeddings = if self.normalize {
+            normalize_rows(pooled)
+        } else {
+            pooled
+        };
+
+        Ok(sentence_embeddings
+            .outer_iter()
+            .map(|row| row.to_vec())
+            .collect())
+    }
+
+    /// Sets the pooling strategy used to reduce token embeddings to a
+    /// sentence vector. Defaults to `PoolingConfig::Mean`.
+    pub fn with_pooling(mut self, pooling: PoolingConfig) -> Self {
+        self.pooling = pooling;
+        self
+    }
+
+    /// Controls whether pooled embeddings are L2-normalized. Defaults to
+    /// `true`; disable for models that expect raw pooled vectors.
+    pub fn with_normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Declares that the loaded model is a masked-LM (e.g. a SPLADE
+    /// checkpoint) whose output is MLM logits rather than token embeddings,
+    /// which is required before calling [`ClientSession::embed_sparse`].
+    pub fn with_masked_lm(mut self, is_masked_lm: bool) -> Self {
+        self.is_masked_lm = is_masked_lm;
+        self
+    }
+
+    /// Sets the instruction prefixes instruction-tuned retrieval models
+    /// (E5, BGE, etc.) expect on queries and passages respectively, e.g.
+    /// `("query: ", "passage: ")`. Applied before tokenization by
+    /// [`ClientSession::embed_query`] and [`ClientSession::embed_document`].
+    pub fn with_prefixes(
+        mut self,
+        query_prefix: impl Into<String>,
+        document_prefix: impl Into<String>,
+    ) -> Self {
+        self.query_prefix = query_prefix.into();
+        self.document_prefix = document_prefix.into();
+        self
+    }
+
+    /// Embeds `input` as a search query, prepending the configured query
+    /// prefix (see [`ClientSession::with_prefixes`]).
+    pub fn embed_query(&mut self, input: &str) -> Result<Vec<f32>> {
+        let prefixed = format!("{}{}", self.query_prefix, input);
+        self.embedding(&prefixed)
+    }
+
+    /// Embeds `input` as a document/passage, prepending the configured
+    /// document prefix (see [`ClientSession::with_prefixes`]).
+    pub fn embed_document(&mut self, input: &str) -> Result<Vec<f32>> {
+        let prefixed = format!("{}{}", self.document_prefix, input);
+        self.embedding(&prefixed)
+    }
+
+    /// Sets the `DistributionShift` used by [`ClientSession::calibrated_similarity`]
+    /// to remap this model's raw similarity scores onto a roughly uniform
+    /// [0,1] scale, so downstream search code can use a single fixed
+    /// relevance threshold across models.
+    pub fn with_distribution_shift(mut self, shift: DistributionShift) -> Self {
+        self.distribution_shift = Some(shift);
+        self
+    }
+
+    /// Cosine similarity between two embeddings, remapped through a shifted
+    /// sigmoid via the configured `DistributionShift` (if any); returns the
+    /// raw cosine similarity when no shift has been set. Returns `None`
+    /// instead of panicking if `a` and `b` have different lengths (e.g.
+    /// embeddings from two different models).
+    pub fn calibrated_similarity(&self, a: &[f32], b: &[f32]) -> Option<f32> {
+        let cosine = cosine_similarity(a, b)?;
+
+        Some(match self.distribution_shift {
+            Some(shift) => shift.shift(cosine),
+            None => cosine,
+        })
+    }
+
+    /// Computes a SPLADE-style sparse embedding: for every vocabulary term
+    /// `j`, `s_j = max_i log(1 + relu(logit[i, j]))` over the unmasked
+    /// tokens `i`. Most terms are zero, so only the nonzero weights are
+    /// returned, sorted by descending weight. Returns an error unless the
+    /// session was built with [`ClientSession::with_masked_lm`]`(true)`.
+    pub fn embed_sparse(&mut self, input: &str) -> Result<Vec<(u32, f32)>> {
+        if !self.is_masked_lm {
+            return Err(
+                "embed_sparse requires a masked-LM session; call with_masked_lm(true) when loading the model"
+                    .into(),
+            );
         }
-        Ok(vec)
+
+        let encoding = self.tokenizer.encode(input, true)?;
+        let (input_ids_array, attention_mask_array, token_type_ids_array) =
+            encodings_to_arrays(std::slice::from_ref(&encoding));
+
+        // Run the MLM head; output is logits of shape (1, seq_len, vocab_size)
+        let outputs: Vec<OrtOwnedTensor<f32, _>> = self.session.run(vec![
+            input_ids_array,
+            attention_mask_array.clone(),
+            token_type_ids_array,
+        ])?;
+
+        let logits = outputs[0].to_owned();
+        let vocab_size = logits.shape()[2];
+        let mask_expanded = expand_mask(&attention_mask_array, vocab_size);
+
+        // log(1 + relu(x)), then push masked-out positions to -inf so the
+        // per-term max below only ever sees unmasked tokens.
+        let activated = logits.mapv(|x| (1.0 + x.max(0.0)).ln());
+        let masked = activated - (1.0 - &mask_expanded) * 1e9;
+        let term_weights = masked
+            .fold_axis(Axis(1), f32::NEG_INFINITY, |&acc, &x| acc.max(x))
+            .index_axis(Axis(0), 0)
+            .to_owned();
+
+        let mut sparse: Vec<(u32, f32)> = term_weights
+            .iter()
+            .enumerate()
+            .filter(|&(_, &weight)| weight > 0.0)
+            .map(|(token_id, &weight)| (token_id as u32, weight))
+            .collect();
+        sparse.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+        Ok(sparse)
+    }
+
+    /// Maps a vocabulary id returned by [`ClientSession::embed_sparse`] back
+    /// to its token string.
+    pub fn sparse_term(&self, token_id: u32) -> Option<String> {
+        self.tokenizer.id_to_token(token_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::arr3;
+
+    #[test]
+    fn mean_pool_averages_unmasked_tokens() {
+        let token_embeddings = arr3(&[[[1.0, 1.0], [3.0, 3.0], [100.0, 100.0]]]).into_dyn();
+        let attention_mask = Array2::from_shape_vec((1, 3), vec![1i64, 1, 0]).unwrap();
+
+        let pooled = mean_pool(&token_embeddings, &attention_mask);
+
+        assert_eq!(pooled.into_raw_vec(), vec![2.0, 2.0]);
+    }
+
+    #[test]
+    fn cls_pool_takes_first_token() {
+        let token_embeddings = arr3(&[[[5.0, 6.0], [7.0, 8.0]]]).into_dyn();
+
+        let pooled = cls_pool(&token_embeddings);
+
+        assert_eq!(pooled.into_raw_vec(), vec![5.0, 6.0]);
+    }
+
+    #[test]
+    fn max_pool_ignores_masked_tokens() {
+        let token_embeddings = arr3(&[[[1.0, -1.0], [3.0, -3.0], [100.0, -100.0]]]).into_dyn();
+        let attention_mask = Array2::from_shape_vec((1, 3), vec![1i64, 1, 0]).unwrap();
+
+        let pooled = max_pool(&token_embeddings, &attention_mask);
+
+        assert_eq!(pooled.into_raw_vec(), vec![3.0, -1.0]);
+    }
+
+    #[test]
+    fn normalize_rows_produces_unit_vectors() {
+        let embeddings = Array2::from_shape_vec((1, 2), vec![3.0, 4.0])
+            .unwrap()
+            .into_dyn();
+
+        let normalized = normalize_rows(embeddings).into_raw_vec();
+
+        assert!((normalized[0] - 0.6).abs() < 1e-6);
+        assert!((normalized[1] - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn distribution_shift_centers_at_mean() {
+        let shift = DistributionShift {
+            mean: 0.5,
+            std: 0.1,
+        };
+
+        assert!((shift.shift(0.5) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cosine_similarity_rejects_mismatched_lengths() {
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[1.0, 0.0, 0.0]), None);
+    }
+
+    #[test]
+    fn cosine_similarity_of_identical_vectors_is_one() {
+        let similarity = cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]).unwrap();
+
+        assert!((similarity - 1.0).abs() < 1e-6);
     }
 }